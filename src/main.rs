@@ -5,7 +5,8 @@ use glium::glutin::ContextBuilder;
 use glium::glutin::dpi::LogicalSize;
 use glium::glutin::event_loop::{ControlFlow, EventLoop};
 use glium::glutin::window::WindowBuilder;
-use glium::glutin::event::{Event, StartCause, WindowEvent};
+use glium::glutin::event::{ElementState, Event, KeyboardInput, MouseScrollDelta, StartCause, VirtualKeyCode, WindowEvent};
+use glium::texture::{RawImage2d, SrgbTexture2d};
 use glium::{implement_vertex, uniform, Program, Surface, VertexBuffer};
 use nalgebra_glm as glm;
 use std::error::Error;
@@ -16,14 +17,190 @@ const WIN_WIDTH: f32 = 800.0;
 const WIN_HEIGHT: f32 = 600.0;
 const DTHETA: f32 = 0.02;
 const PI2: f32 = PI * 2.0;
+const CUBE_TEXTURE_PATH: &str = "assets/cube.png";
+const ORBIT_SPEED: f32 = 0.03;
+const ZOOM_SPEED: f32 = 0.3;
+const MIN_RADIUS: f32 = 1.5;
+const MAX_RADIUS: f32 = 20.0;
+const MAX_ELEVATION: f32 = PI / 2.0 - 0.01;
+
+const LIGHT_DIR: [f32; 3] = [10.0, 5.0, 7.0];
+const AMBIENT: [f32; 3] = [0.3, 0.3, 0.3];
+
+const INSTANCE_GRID: usize = 10;
+const INSTANCE_SPACING: f32 = 1.5;
 
 #[derive(Copy, Clone)]
 struct Vertex {
     coord: [f32; 3],
     rgba: [f32; 4],
+    tex_coords: [f32; 2],
+    normal: [f32; 3],
+}
+
+implement_vertex!(Vertex, coord, rgba, tex_coords, normal);
+
+#[derive(Copy, Clone)]
+struct Instance {
+    offset: [f32; 3],
+    rotation_axis: [f32; 3],
+    phase: f32,
+}
+
+implement_vertex!(Instance, offset, rotation_axis, phase);
+
+fn build_instance_field(display: &Display) -> Result<VertexBuffer<Instance>, Box<dyn Error>> {
+    let half = (INSTANCE_GRID as f32 - 1.0) / 2.0;
+
+    let instances: Vec<Instance> = (0..INSTANCE_GRID * INSTANCE_GRID)
+        .map(|i| {
+            let row = (i / INSTANCE_GRID) as f32;
+            let col = (i % INSTANCE_GRID) as f32;
+
+            Instance {
+                offset: [(col - half) * INSTANCE_SPACING, 0.0, (row - half) * INSTANCE_SPACING],
+                rotation_axis: [0.0, 1.0, (i as f32 * 0.3).sin()],
+                phase: i as f32 * 0.3,
+            }
+        })
+        .collect();
+
+    Ok(VertexBuffer::new(display, &instances)?)
+}
+
+/// Single instance at the origin, spinning about (0, 1, -1) with `theta`,
+/// used when rendering a user-supplied mesh, so
+/// `load_mesh` results stay a single inspectable model instead of being
+/// tiled across the instanced field.
+fn build_single_instance(display: &Display) -> Result<VertexBuffer<Instance>, Box<dyn Error>> {
+    let instances = [Instance { offset: [0.0, 0.0, 0.0], rotation_axis: [0.0, 1.0, -1.0], phase: 0.0 }];
+
+    Ok(VertexBuffer::new(display, &instances)?)
+}
+
+const PLACEHOLDER_TEXTURE_SIZE: u32 = 64;
+const PLACEHOLDER_TEXTURE_TILE: u32 = 8;
+
+/// Checkerboard texture that `load_texture` uses when its `path` can't be
+/// opened (a file that opens but fails to decode is an error), so the demo still
+/// renders a textured cube without shipping a binary PNG asset.
+fn placeholder_texture() -> RawImage2d<'static, u8> {
+    let mut data = Vec::with_capacity((PLACEHOLDER_TEXTURE_SIZE * PLACEHOLDER_TEXTURE_SIZE * 4) as usize);
+
+    for y in 0..PLACEHOLDER_TEXTURE_SIZE {
+        for x in 0..PLACEHOLDER_TEXTURE_SIZE {
+            let light = ((x / PLACEHOLDER_TEXTURE_TILE) + (y / PLACEHOLDER_TEXTURE_TILE)) % 2 == 0;
+            let shade = if light { 220u8 } else { 60u8 };
+            data.extend_from_slice(&[shade, shade, shade, 255]);
+        }
+    }
+
+    RawImage2d::from_raw_rgba(data, (PLACEHOLDER_TEXTURE_SIZE, PLACEHOLDER_TEXTURE_SIZE))
+}
+
+fn load_texture(path: &str, display: &Display) -> Result<SrgbTexture2d, Box<dyn Error>> {
+    let raw = match std::fs::File::open(path) {
+        Ok(file) => {
+            let image = image::load(std::io::BufReader::new(file), image::ImageFormat::Png)?.to_rgba8();
+            let dims = image.dimensions();
+            RawImage2d::from_raw_rgba_reversed(&image.into_raw(), dims)
+        },
+        Err(_) => placeholder_texture(),
+    };
+
+    Ok(SrgbTexture2d::new(display, raw)?)
 }
 
-implement_vertex!(Vertex, coord, rgba);
+fn build_default_cube(display: &Display) -> Result<(VertexBuffer<Vertex>, IndexBuffer<u32>), Box<dyn Error>> {
+    let cube: [Vertex; 24] = [
+        // Front face
+        Vertex { coord: [0.5, 0.5, 0.5], rgba: [1.0, 0.0, 0.0, 1.0], tex_coords: [1.0, 1.0], normal: [0.0, 0.0, 1.0] },
+        Vertex { coord: [0.5, -0.5, 0.5], rgba: [1.0, 0.0, 0.0, 1.0], tex_coords: [1.0, 0.0], normal: [0.0, 0.0, 1.0] },
+        Vertex { coord: [-0.5, -0.5, 0.5], rgba: [1.0, 0.0, 0.0, 1.0], tex_coords: [0.0, 0.0], normal: [0.0, 0.0, 1.0] },
+        Vertex { coord: [-0.5, 0.5, 0.5], rgba: [1.0, 0.0, 0.0, 1.0], tex_coords: [0.0, 1.0], normal: [0.0, 0.0, 1.0] },
+
+        // Back face
+        Vertex { coord: [-0.5, 0.5, -0.5], rgba: [0.0, 0.0, 1.0, 1.0], tex_coords: [1.0, 1.0], normal: [0.0, 0.0, -1.0] },
+        Vertex { coord: [-0.5, -0.5, -0.5], rgba: [0.0, 0.0, 1.0, 1.0], tex_coords: [1.0, 0.0], normal: [0.0, 0.0, -1.0] },
+        Vertex { coord: [0.5, -0.5, -0.5], rgba: [0.0, 0.0, 1.0, 1.0], tex_coords: [0.0, 0.0], normal: [0.0, 0.0, -1.0] },
+        Vertex { coord: [0.5, 0.5, -0.5], rgba: [0.0, 0.0, 1.0, 1.0], tex_coords: [0.0, 1.0], normal: [0.0, 0.0, -1.0] },
+
+        // Left face
+        Vertex { coord: [-0.5, 0.5, 0.5], rgba: [0.0, 1.0, 0.0, 1.0], tex_coords: [1.0, 1.0], normal: [-1.0, 0.0, 0.0] },
+        Vertex { coord: [-0.5, -0.5, 0.5], rgba: [0.0, 1.0, 0.0, 1.0], tex_coords: [1.0, 0.0], normal: [-1.0, 0.0, 0.0] },
+        Vertex { coord: [-0.5, -0.5, -0.5], rgba: [0.0, 1.0, 0.0, 1.0], tex_coords: [0.0, 0.0], normal: [-1.0, 0.0, 0.0] },
+        Vertex { coord: [-0.5, 0.5, -0.5], rgba: [0.0, 1.0, 0.0, 1.0], tex_coords: [0.0, 1.0], normal: [-1.0, 0.0, 0.0] },
+
+        // Right face
+        Vertex { coord: [0.5, 0.5, -0.5], rgba: [1.0, 1.0, 0.0, 1.0], tex_coords: [1.0, 1.0], normal: [1.0, 0.0, 0.0] },
+        Vertex { coord: [0.5, -0.5, -0.5], rgba: [1.0, 1.0, 0.0, 1.0], tex_coords: [1.0, 0.0], normal: [1.0, 0.0, 0.0] },
+        Vertex { coord: [0.5, -0.5, 0.5], rgba: [1.0, 1.0, 0.0, 1.0], tex_coords: [0.0, 0.0], normal: [1.0, 0.0, 0.0] },
+        Vertex { coord: [0.5, 0.5, 0.5], rgba: [1.0, 1.0, 0.0, 1.0], tex_coords: [0.0, 1.0], normal: [1.0, 0.0, 0.0] },
+
+        // Top face
+        Vertex { coord: [-0.5, 0.5, -0.5], rgba: [1.0, 0.0, 1.0, 1.0], tex_coords: [1.0, 1.0], normal: [0.0, 1.0, 0.0] },
+        Vertex { coord: [-0.5, 0.5, 0.5], rgba: [1.0, 0.0, 1.0, 1.0], tex_coords: [1.0, 0.0], normal: [0.0, 1.0, 0.0] },
+        Vertex { coord: [0.5, 0.5, 0.5], rgba: [1.0, 0.0, 1.0, 1.0], tex_coords: [0.0, 0.0], normal: [0.0, 1.0, 0.0] },
+        Vertex { coord: [0.5, 0.5, -0.5], rgba: [1.0, 0.0, 1.0, 1.0], tex_coords: [0.0, 1.0], normal: [0.0, 1.0, 0.0] },
+
+        // Bottom face
+        Vertex { coord: [-0.5, -0.5, 0.5], rgba: [0.0, 1.0, 1.0, 1.0], tex_coords: [1.0, 1.0], normal: [0.0, -1.0, 0.0] },
+        Vertex { coord: [-0.5, -0.5, -0.5], rgba: [0.0, 1.0, 1.0, 1.0], tex_coords: [1.0, 0.0], normal: [0.0, -1.0, 0.0] },
+        Vertex { coord: [0.5, -0.5, -0.5], rgba: [0.0, 1.0, 1.0, 1.0], tex_coords: [0.0, 0.0], normal: [0.0, -1.0, 0.0] },
+        Vertex { coord: [0.5, -0.5, 0.5], rgba: [0.0, 1.0, 1.0, 1.0], tex_coords: [0.0, 1.0], normal: [0.0, -1.0, 0.0] },
+    ];
+
+    let indices: [u32; 36] = [
+        0, 1, 2, 0, 2, 3,
+        4, 5, 6, 4, 6, 7,
+        8, 9, 10, 8, 10, 11,
+        12, 13, 14, 12, 14, 15,
+        16, 17, 18, 16, 18, 19,
+        20, 21, 22, 20, 22, 23,
+    ];
+
+    let vbo = VertexBuffer::new(display, &cube)?;
+    let ibuf = IndexBuffer::new(display, PrimitiveType::TrianglesList, &indices)?;
+
+    Ok((vbo, ibuf))
+}
+
+fn load_mesh(path: &str, display: &Display) -> Result<(VertexBuffer<Vertex>, IndexBuffer<u32>), Box<dyn Error>> {
+    let (models, _materials) = tobj::load_obj(
+        path,
+        &tobj::LoadOptions { triangulate: true, single_index: true, ..Default::default() }
+    )?;
+
+    let mesh = &models.first().ok_or("obj file contains no meshes")?.mesh;
+
+    let vertex_count = mesh.positions.len() / 3;
+    let vertices: Vec<Vertex> = (0..vertex_count)
+        .map(|i| {
+            let coord = [
+                mesh.positions[i * 3],
+                mesh.positions[i * 3 + 1],
+                mesh.positions[i * 3 + 2],
+            ];
+            let tex_coords = if mesh.texcoords.len() >= (i + 1) * 2 {
+                [mesh.texcoords[i * 2], mesh.texcoords[i * 2 + 1]]
+            } else {
+                [0.0, 0.0]
+            };
+            let normal = if mesh.normals.len() >= (i + 1) * 3 {
+                [mesh.normals[i * 3], mesh.normals[i * 3 + 1], mesh.normals[i * 3 + 2]]
+            } else {
+                [0.0, 0.0, 0.0]
+            };
+
+            Vertex { coord, rgba: [1.0, 1.0, 1.0, 1.0], tex_coords, normal }
+        })
+        .collect();
+
+    let vbo = VertexBuffer::new(display, &vertices)?;
+    let ibuf = IndexBuffer::new(display, PrimitiveType::TrianglesList, &mesh.indices)?;
+
+    Ok((vbo, ibuf))
+}
 
 fn main() -> Result<(), Box<dyn Error>> {
     let context = ContextBuilder::new()
@@ -35,31 +212,20 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let display = Display::new(window, context, &event_loop)?;
 
-    let cube: [Vertex; 8] = [
-        // Front face
-        Vertex { coord: [0.5, 0.5, 0.5], rgba: [1.0, 0.0, 0.0, 1.0] },
-        Vertex { coord: [0.5, -0.5, 0.5], rgba: [1.0, 0.0, 0.0, 1.0] },
-        Vertex { coord: [-0.5, -0.5, 0.5], rgba: [1.0, 0.0, 0.0, 1.0] },
-        Vertex { coord: [-0.5, 0.5, 0.5], rgba: [1.0, 0.0, 0.0, 1.0] },
-
-        // Back face
-        Vertex { coord: [0.5, 0.5, -0.5], rgba: [0.0, 0.0, 1.0, 1.0] },
-        Vertex { coord: [0.5, -0.5, -0.5], rgba: [0.0, 0.0, 1.0, 1.0] },
-        Vertex { coord: [-0.5, -0.5, -0.5], rgba: [0.0, 0.0, 1.0, 1.0] },
-        Vertex { coord: [-0.5, 0.5, -0.5], rgba: [0.0, 0.0, 1.0, 1.0] },
-    ];
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut fractal_mode = args.iter().any(|a| a == "--fractal");
+    let model_path = args.iter().find(|a| !a.starts_with("--")).cloned();
 
-    let indices: [u8; 36] = [
-        0, 1, 2, 0, 2, 3,
-        0, 1, 5, 0, 4, 5,
-        2, 3, 6, 3, 6, 7,
-        5, 6, 7, 4, 5, 7,
-        0, 3, 7, 0, 4, 7,
-        1, 2, 6, 1, 5, 6
-    ];
+    let (vbo, ibuf) = match &model_path {
+        Some(path) => load_mesh(path, &display)?,
+        None => build_default_cube(&display)?,
+    };
 
-    let vbo = VertexBuffer::new(&display, &cube)?;
-    let ibuf = IndexBuffer::new(&display, PrimitiveType::TrianglesList, &indices)?;
+    let texture = load_texture(CUBE_TEXTURE_PATH, &display)?;
+    let instances = match &model_path {
+        Some(_) => build_single_instance(&display)?,
+        None => build_instance_field(&display)?,
+    };
 
     let vert_shader_glsl = r#"
         #version 330 core
@@ -68,13 +234,41 @@ fn main() -> Result<(), Box<dyn Error>> {
         uniform mat4 m;
         uniform mat4 v;
         uniform mat4 p;
+        uniform float theta;
 
         in vec4 rgba;
+        in vec2 tex_coords;
+        in vec3 normal;
+        in vec3 offset;
+        in vec3 rotation_axis;
+        in float phase;
         out vec4 color;
+        out vec2 v_tex_coords;
+        out vec3 v_normal;
+
+        mat3 instance_rotation(vec3 axis, float angle) {
+            vec3 a = normalize(axis);
+            float s = sin(angle);
+            float c = cos(angle);
+            float oc = 1.0 - c;
+
+            return mat3(
+                oc * a.x * a.x + c,       oc * a.x * a.y - a.z * s, oc * a.z * a.x + a.y * s,
+                oc * a.x * a.y + a.z * s, oc * a.y * a.y + c,       oc * a.y * a.z - a.x * s,
+                oc * a.z * a.x - a.y * s, oc * a.y * a.z + a.x * s, oc * a.z * a.z + c
+            );
+        }
 
         void main() {
-            gl_Position = p * v * m * vec4(coord, 1.0);
+            // inst_rot is always a pure rotation, so it doubles as its own
+            // normal transform -- no separate inverse-transpose needed.
+            mat3 inst_rot = instance_rotation(rotation_axis, phase + theta);
+            vec3 world_coord = inst_rot * coord + offset;
+
+            gl_Position = p * v * m * vec4(world_coord, 1.0);
             color = rgba;
+            v_tex_coords = tex_coords;
+            v_normal = inst_rot * normal;
         }
     "#;
 
@@ -82,10 +276,19 @@ fn main() -> Result<(), Box<dyn Error>> {
         # version 150
 
         in vec4 color;
+        in vec2 v_tex_coords;
+        in vec3 v_normal;
         out vec4 FragColor;
 
+        uniform sampler2D tex;
+        uniform vec3 light_dir;
+        uniform vec3 ambient;
+
         void main() {
-            FragColor = color;
+            float lambert = max(dot(normalize(v_normal), light_dir), 0.0);
+            vec3 lighting = ambient + lambert;
+            vec4 base_color = texture(tex, v_tex_coords) * color;
+            FragColor = vec4(lighting * base_color.rgb, base_color.a);
         }
     "#;
 
@@ -96,6 +299,63 @@ fn main() -> Result<(), Box<dyn Error>> {
         None
     )?;
 
+    let fractal_vert_shader_glsl = r#"
+        #version 330 core
+        layout (location = 0) in vec3 coord;
+
+        uniform mat4 m;
+        uniform mat4 v;
+        uniform mat4 p;
+
+        out vec2 v_c;
+
+        void main() {
+            gl_Position = p * v * m * vec4(coord, 1.0);
+            v_c = coord.xy * 3.0;
+        }
+    "#;
+
+    let fractal_frag_shader_glsl = r#"
+        # version 150
+
+        in vec2 v_c;
+        out vec4 FragColor;
+
+        uniform vec2 z0;
+
+        const int maxiter = 64;
+
+        void main() {
+            float zx = z0.x;
+            float zy = z0.y;
+            int iter = 0;
+
+            for (int i = 0; i < maxiter; i++) {
+                if (zx * zx + zy * zy > 4.0) {
+                    break;
+                }
+                float xtemp = zx * zx - zy * zy + v_c.x;
+                zy = 2.0 * zx * zy + v_c.y;
+                zx = xtemp;
+                iter++;
+            }
+
+            if (iter == maxiter) {
+                FragColor = vec4(0.05, 0.05, 0.08, 1.0);
+            } else {
+                float t = float(iter) / float(maxiter);
+                FragColor = vec4(t, t * 0.5, 1.0 - t, 1.0);
+            }
+        }
+    "#;
+
+    let fractal_program = Program::from_source(
+        &display,
+        fractal_vert_shader_glsl,
+        fractal_frag_shader_glsl,
+        None
+    )?;
+
     let draw_params = DrawParameters {
         depth: Depth {
             test: DepthTest::IfLess,
@@ -105,17 +365,13 @@ fn main() -> Result<(), Box<dyn Error>> {
         .. Default::default()
     };
 
-    let v_matrix = glm::translate(
-        &glm::TMat4::identity(),
-        &glm::vec3(0.0, 0.0, -3.5)
-    );
-
-    let p_matrix = glm::perspective(WIN_WIDTH / WIN_HEIGHT, PI / 4.0, 0.1, 100.0);
-
-    let view: [[f32; 4]; 4] = *v_matrix.as_ref();
-    let projection: [[f32; 4]; 4] = *p_matrix.as_ref();
+    let light_dir: [f32; 3] = *glm::normalize(&glm::make_vec3(&LIGHT_DIR)).as_ref();
 
     let mut theta = 0.0;
+    let mut aspect = WIN_WIDTH / WIN_HEIGHT;
+    let mut azimuth: f32 = 0.0;
+    let mut elevation: f32 = 0.0;
+    let mut radius: f32 = 3.5;
 
     event_loop.run(move |event, _, ctrlflow| {
         let time_to_next_frame = Instant::now() + Duration::from_nanos(16_666_667);
@@ -127,7 +383,37 @@ fn main() -> Result<(), Box<dyn Error>> {
                     *ctrlflow = ControlFlow::Exit;
                     return;
                 },
-                _ => (),
+
+                WindowEvent::Resized(size) => {
+                    aspect = size.width as f32 / size.height as f32;
+                    return;
+                },
+
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput { state: ElementState::Pressed, virtual_keycode: Some(key), .. },
+                    ..
+                } => {
+                    match key {
+                        VirtualKeyCode::Left | VirtualKeyCode::A => azimuth -= ORBIT_SPEED,
+                        VirtualKeyCode::Right | VirtualKeyCode::D => azimuth += ORBIT_SPEED,
+                        VirtualKeyCode::Up | VirtualKeyCode::W => elevation = (elevation + ORBIT_SPEED).min(MAX_ELEVATION),
+                        VirtualKeyCode::Down | VirtualKeyCode::S => elevation = (elevation - ORBIT_SPEED).max(-MAX_ELEVATION),
+                        VirtualKeyCode::F => fractal_mode = !fractal_mode,
+                        _ => return,
+                    }
+                    return;
+                },
+
+                WindowEvent::MouseWheel { delta, .. } => {
+                    let scroll = match delta {
+                        MouseScrollDelta::LineDelta(_, y) => y,
+                        MouseScrollDelta::PixelDelta(pos) => pos.y as f32,
+                    };
+                    radius = (radius - scroll * ZOOM_SPEED).clamp(MIN_RADIUS, MAX_RADIUS);
+                    return;
+                },
+
+                _ => return,
             },
 
             Event::NewEvents(c) => match c {
@@ -139,23 +425,47 @@ fn main() -> Result<(), Box<dyn Error>> {
             _ => return,
         }
 
-        let mut target = display.draw();
+        let eye = glm::vec3(
+            radius * elevation.cos() * azimuth.cos(),
+            radius * elevation.sin(),
+            radius * elevation.cos() * azimuth.sin()
+        );
+        let v_matrix = glm::look_at(&eye, &glm::vec3(0.0, 0.0, 0.0), &glm::vec3(0.0, 1.0, 0.0));
+        let p_matrix = glm::perspective(aspect, PI / 4.0, 0.1, 100.0);
 
-        target.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
+        let view: [[f32; 4]; 4] = *v_matrix.as_ref();
+        let projection: [[f32; 4]; 4] = *p_matrix.as_ref();
 
-        let m_matrix = glm::rotate(
-            &glm::TMat4::identity(),
-            theta,
-            &glm::vec3(0.0, 1.0, -1.0)
-        );
+        let mut target = display.draw();
 
-        let model: [[f32; 4]; 4] = *m_matrix.as_ref();
-        let uniforms = uniform! { m: model, v: view, p: projection };
+        target.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
 
-        target.draw(
-            &vbo, &ibuf, &program,
-            &uniforms, &draw_params
-        ).unwrap();
+        if fractal_mode {
+            let m_matrix = glm::rotate(
+                &glm::TMat4::identity(),
+                theta,
+                &glm::vec3(0.0, 1.0, -1.0)
+            );
+            let model: [[f32; 4]; 4] = *m_matrix.as_ref();
+            let z0 = [theta.cos() * 0.5, theta.sin() * 0.5];
+            let uniforms = uniform! { m: model, v: view, p: projection, z0: z0 };
+
+            target.draw(
+                &vbo, &ibuf, &fractal_program,
+                &uniforms, &draw_params
+            ).unwrap();
+        } else {
+            let model: [[f32; 4]; 4] = *glm::TMat4::<f32>::identity().as_ref();
+            let uniforms = uniform! {
+                m: model, v: view, p: projection, tex: &texture,
+                light_dir: light_dir, ambient: AMBIENT, theta: theta
+            };
+
+            target.draw(
+                (&vbo, instances.per_instance().unwrap()), &ibuf, &program,
+                &uniforms, &draw_params
+            ).unwrap();
+        }
 
         target.finish().unwrap();
 